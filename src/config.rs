@@ -0,0 +1,113 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use vulkano::device::physical::PhysicalDeviceType;
+use vulkano::swapchain::PresentMode;
+
+// Mirrors `vulkano::device::physical::PhysicalDeviceType`, which isn't
+// `Deserialize`, so config files spell out device type preference with
+// these variants instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceTypePreference {
+    DiscreteGpu,
+    IntegratedGpu,
+    VirtualGpu,
+    Cpu,
+    Other,
+}
+
+impl DeviceTypePreference {
+    fn matches(self, device_type: PhysicalDeviceType) -> bool {
+        matches!(
+            (self, device_type),
+            (DeviceTypePreference::DiscreteGpu, PhysicalDeviceType::DiscreteGpu)
+                | (DeviceTypePreference::IntegratedGpu, PhysicalDeviceType::IntegratedGpu)
+                | (DeviceTypePreference::VirtualGpu, PhysicalDeviceType::VirtualGpu)
+                | (DeviceTypePreference::Cpu, PhysicalDeviceType::Cpu)
+                | (DeviceTypePreference::Other, PhysicalDeviceType::Other)
+        )
+    }
+}
+
+// Mirrors `vulkano::swapchain::PresentMode` for the same reason.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentModePreference {
+    Immediate,
+    Mailbox,
+    Fifo,
+    FifoRelaxed,
+}
+
+impl PresentModePreference {
+    pub fn to_vulkan(self) -> PresentMode {
+        match self {
+            PresentModePreference::Immediate => PresentMode::Immediate,
+            PresentModePreference::Mailbox => PresentMode::Mailbox,
+            PresentModePreference::Fifo => PresentMode::Fifo,
+            PresentModePreference::FifoRelaxed => PresentMode::FifoRelaxed,
+        }
+    }
+}
+
+// Renderer configuration loaded from a `.scm` (S-expression, via
+// `serde_lexpr`) or `.toml` file at startup instead of the hardcoded asset
+// paths, device preference, present mode, and clear color `init_vulkan`
+// used to assume.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RendererConfig {
+    pub asset_root: PathBuf,
+    pub device_type_preference: Vec<DeviceTypePreference>,
+    pub present_mode: PresentModePreference,
+    pub min_image_count: Option<u32>,
+    pub clear_color: [f32; 4],
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            asset_root: PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/assets")),
+            device_type_preference: vec![
+                DeviceTypePreference::DiscreteGpu,
+                DeviceTypePreference::IntegratedGpu,
+                DeviceTypePreference::VirtualGpu,
+                DeviceTypePreference::Cpu,
+                DeviceTypePreference::Other,
+            ],
+            present_mode: PresentModePreference::Fifo,
+            min_image_count: None,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl RendererConfig {
+    // Loads a `.scm` (s-expression, via `serde_lexpr`) or `.toml` config
+    // based on `path`'s extension. Returns `Err` with a human-readable
+    // diagnostic instead of panicking, so the caller can fall back to
+    // `RendererConfig::default()` on a missing or malformed file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("scm") => serde_lexpr::from_str(&source)
+                .map_err(|e| format!("failed to parse {}: {}", path.display(), e)),
+            Some("toml") => toml::from_str(&source)
+                .map_err(|e| format!("failed to parse {}: {}", path.display(), e)),
+            other => Err(format!("unsupported config extension {:?} in {}", other, path.display())),
+        }
+    }
+
+    // Lower is more preferred; a device type absent from the list ranks
+    // last, same as the old hardcoded match in `select_physical_device`.
+    pub fn device_rank(&self, device_type: PhysicalDeviceType) -> usize {
+        self.device_type_preference
+            .iter()
+            .position(|preference| preference.matches(device_type))
+            .unwrap_or(self.device_type_preference.len())
+    }
+}
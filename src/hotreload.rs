@@ -0,0 +1,79 @@
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::time::Duration;
+
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebounceEventResult, Debouncer};
+use notify::RecommendedWatcher;
+
+use vulkano::{device::Device, shader::ShaderModule};
+
+// Debounce window for `ShaderWatcher`: a single save can emit several
+// filesystem events, so we coalesce anything within this window into one
+// reload instead of recompiling multiple times.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+// Watches `assets/shaders` for changes and lets `main`'s event loop poll for
+// them without blocking, mirroring the `window_resized` flag it already has.
+pub struct ShaderWatcher {
+    _debouncer: Debouncer<RecommendedWatcher>,
+    events: Receiver<DebounceEventResult>,
+}
+
+impl ShaderWatcher {
+    pub fn new(shader_dir: &Path) -> Self {
+        let (tx, events) = channel();
+        let mut debouncer = new_debouncer(DEBOUNCE, tx).expect("Failed to create shader watcher");
+        debouncer
+            .watcher()
+            .watch(shader_dir, RecursiveMode::Recursive)
+            .expect("Failed to watch shader directory");
+
+        Self { _debouncer: debouncer, events }
+    }
+
+    // Non-blocking: drains any pending filesystem events and reports whether
+    // at least one of them looked like a real change.
+    pub fn poll_changed(&self) -> bool {
+        let mut changed = false;
+        while let Ok(result) = self.events.try_recv() {
+            if result.is_ok() {
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+// Recompiles `path`'s GLSL source to SPIR-V with shaderc and loads it as a
+// `ShaderModule`. Returns `Err` (with a human-readable diagnostic) instead of
+// panicking so the caller can keep the last-good shader on a bad save.
+fn compile_shader(device: Arc<Device>, path: &Path, kind: shaderc::ShaderKind) -> Result<Arc<ShaderModule>, String> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+
+    let compiler = shaderc::Compiler::new().ok_or("failed to initialize shaderc")?;
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("shader");
+
+    let binary = compiler
+        .compile_into_spirv(&source, kind, file_name, "main", None)
+        .map_err(|e| format!("failed to compile {}: {}", path.display(), e))?;
+
+    unsafe {
+        ShaderModule::from_words(device, binary.as_binary())
+            .map_err(|e| format!("failed to load compiled {}: {}", path.display(), e))
+    }
+}
+
+// Recompiles both the vertex and fragment shaders for the triangle demo.
+// Either failing aborts the reload so the renderer keeps drawing with the
+// shaders it already has.
+pub fn recompile_shaders(
+    device: Arc<Device>,
+    vs_path: &Path,
+    fs_path: &Path,
+) -> Result<(Arc<ShaderModule>, Arc<ShaderModule>), String> {
+    let vs = compile_shader(device.clone(), vs_path, shaderc::ShaderKind::Vertex)?;
+    let fs = compile_shader(device, fs_path, shaderc::ShaderKind::Fragment)?;
+    Ok((vs, fs))
+}
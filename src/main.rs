@@ -1,8 +1,13 @@
 mod renderer;
+mod texture;
+mod hotreload;
+mod render_graph;
+mod config;
 
 use vulkano::{
     pipeline::graphics::viewport::Viewport,
     buffer::{BufferUsage, CpuAccessibleBuffer},
+    sampler::{Filter, SamplerAddressMode, SamplerMipmapMode},
     sync::{FenceSignalFuture, GpuFuture},
     swapchain::{self, AcquireError},
     sync::{self, FlushError},
@@ -13,16 +18,33 @@ use winit::{
     event_loop::ControlFlow,
 };
 
+use vulkano::image::{view::ImageView, ImmutableImage};
+use vulkano::pipeline::PipelineBindPoint;
+use vulkano::sampler::Sampler;
+use vulkano::shader::ShaderModule;
+
 use bytemuck::{Zeroable, Pod};
 
+use std::path::Path;
 use std::sync::Arc;
 
 #[repr(C)]
 #[derive(Default, Copy, Clone, Zeroable, Pod)]
 struct Vertex {
     position: [f32; 2],
+    tex_coord: [f32; 2],
 }
 
+// How many frames the host is allowed to queue ahead of the GPU, independent
+// of how many images the swapchain happens to own. Two lets the CPU prepare
+// the next frame while the GPU is still presenting the previous one, without
+// letting it run arbitrarily far ahead.
+const MAX_FRAMES_IN_FLIGHT: usize = 2;
+
+// looked up relative to the crate root; falls back to `RendererConfig::default`
+// (and a logged diagnostic) if it's missing
+const CONFIG_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/renderer_config.scm");
+
 //load our shaders at compile time
 mod vs {
     vulkano_shaders::shader! {
@@ -38,73 +60,140 @@ mod fs {
     }
 }
 
+// Builds the graph for the demo: a single node drawing the triangle straight
+// to the swapchain image, with its own depth buffer. Re-run after every
+// resize (and every shader hot-reload) since the pipeline and framebuffers
+// it produces are tied to the swapchain images it was compiled against.
+fn build_frame_graph(
+    surface_binding: &renderer::SurfaceBinding,
+    swapchain_binding: &renderer::SwapchainBinding,
+    vs: Arc<ShaderModule>,
+    fs: Arc<ShaderModule>,
+    viewport: Viewport,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    texture_view: Arc<ImageView<ImmutableImage>>,
+    sampler: Arc<Sampler>,
+    clear_color: [f32; 4],
+) -> render_graph::RenderGraph {
+    let mut builder = render_graph::RenderGraphBuilder::new(surface_binding.device.clone());
+    let swapchain_output = builder.swapchain_resource();
+
+    let pipeline_device = surface_binding.device.clone();
+    let pipeline_factory: render_graph::PipelineFactory = Box::new(move |render_pass, depth_enabled| {
+        renderer::get_pipeline::<Vertex>(pipeline_device, vs, fs, render_pass, viewport, depth_enabled)
+    });
+
+    let record: render_graph::RecordFn = Arc::new(move |builder, _image_i, pipeline| {
+        let descriptor_set = texture::build_descriptor_set(pipeline.clone(), 0, texture_view.clone(), sampler.clone());
+        builder
+            .bind_descriptor_sets(PipelineBindPoint::Graphics, pipeline.layout().clone(), 0, descriptor_set)
+            .bind_vertex_buffers(0, vertex_buffer.clone())
+            .draw(vertex_buffer.read().unwrap().len() as u32, 1, 0, 0)
+            .unwrap();
+    });
+
+    builder.add_node(
+        "triangle",
+        vec![],
+        vec![swapchain_output],
+        Some(renderer::DEPTH_FORMAT),
+        clear_color,
+        pipeline_factory,
+        record,
+    );
+
+    builder.compile(&swapchain_binding.swapchain_images)
+}
+
 fn main() {
-    
+    let config = config::RendererConfig::load(Path::new(CONFIG_PATH)).unwrap_or_else(|e| {
+        eprintln!("using default renderer config, failed to load {}: {}", CONFIG_PATH, e);
+        config::RendererConfig::default()
+    });
+
     //vulkan initialization
-    let mut state = renderer::init_vulkan();
-    let render_pass = renderer::get_render_pass(state.device.clone(), &state.image_format);
-    let mut framebuffers = renderer::get_frame_buffers(&state.swapchain_images, render_pass.clone());
+    let (mut surface_binding, mut swapchain_binding) = renderer::init_vulkan(&config);
 
-    vulkano::impl_vertex!(Vertex, position);
+    vulkano::impl_vertex!(Vertex, position, tex_coord);
 
     let vertex1 = Vertex {
         position: [-0.5, -0.5],
+        tex_coord: [0.0, 0.0],
     };
     let vertex2 = Vertex {
         position: [0.0, 0.5],
+        tex_coord: [0.5, 1.0],
     };
     let vertex3 = Vertex {
         position: [0.5, -0.25],
+        tex_coord: [1.0, 0.0],
     };
 
     let vertex_buffer = CpuAccessibleBuffer::from_iter(
-        state.device.clone(),
+        surface_binding.device.clone(),
         BufferUsage::vertex_buffer(),
         false,
         vec![vertex1, vertex2, vertex3].into_iter(),
     )
     .unwrap();
-    
-    let vs = vs::load(state.device.clone()).expect("Failed to load vertex shader");
-    let fs = fs::load(state.device.clone()).expect("Failed to load fragment shader");
+
+    let mut vs = vs::load(surface_binding.device.clone()).expect("Failed to load vertex shader");
+    let mut fs = fs::load(surface_binding.device.clone()).expect("Failed to load fragment shader");
+
+    let shader_dir = config.asset_root.join("shaders");
+    let vs_path = shader_dir.join("triangle.vs");
+    let fs_path = shader_dir.join("triangle.fs");
+    let texture_path = config.asset_root.join("textures/wall.png");
+
+    let shader_watcher = hotreload::ShaderWatcher::new(&shader_dir);
+
+    let texture_view = texture::load_texture(surface_binding.queue.clone(), &texture_path);
+    let sampler = texture::build_sampler(
+        surface_binding.device.clone(),
+        Filter::Linear,
+        SamplerMipmapMode::Nearest,
+        SamplerAddressMode::Repeat
+    );
 
     let mut viewport = Viewport {
         origin: [0.0, 0.0],
-        dimensions: state.surface.window().inner_size().into(),
+        dimensions: surface_binding.surface.window().inner_size().into(),
         depth_range: 0.0..1.0,
-    }; 
+    };
 
-    let pipeline = renderer::get_pipeline::<Vertex>(
-        state.device.clone(),
+    let mut frame_graph = build_frame_graph(
+        &surface_binding,
+        &swapchain_binding,
         vs.clone(),
         fs.clone(),
-        render_pass.clone(),
-        viewport.clone()
-    );
-    
-    let mut command_buffers = renderer::get_command_buffers(
-        state.device.clone(),
-        state.queue.clone(),
-        pipeline,
-        &framebuffers,
-        vertex_buffer.clone()
+        viewport.clone(),
+        vertex_buffer.clone(),
+        texture_view.clone(),
+        sampler.clone(),
+        config.clear_color,
     );
 
+    let mut command_buffers = frame_graph.record_command_buffers(surface_binding.device.clone(), surface_binding.queue.clone());
+
     let mut window_resized = false;
 
-    let frames_in_flight = state.swapchain_images.len();
-    let mut fences: Vec<Option<Arc<FenceSignalFuture<_>>>> = vec![None; frames_in_flight];
-    let mut previous_fence_i = 0;
+    // `frame_fences` is the fixed-depth ring the host waits on before reusing
+    // a frame slot; `images_in_flight` separately tracks which frame slot (if
+    // any) is still using a given swapchain image, since the two counts can
+    // differ.
+    let mut frame_fences: Vec<Option<Arc<FenceSignalFuture<_>>>> = vec![None; MAX_FRAMES_IN_FLIGHT];
+    let mut images_in_flight: Vec<Option<usize>> = vec![None; swapchain_binding.swapchain_images.len()];
+    let mut current_frame = 0usize;
 
-    state.event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll; 
+    surface_binding.event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Poll;
 
         match event {
-            Event::WindowEvent { 
-                event: WindowEvent::CloseRequested, 
-                window_id: id 
-            } if id == state.surface.window().id() => {
-                *control_flow = ControlFlow::Exit; 
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                window_id: id
+            } if id == surface_binding.surface.window().id() => {
+                *control_flow = ControlFlow::Exit;
             },
             Event::WindowEvent {
                 event: WindowEvent::Resized(_),
@@ -117,36 +206,43 @@ fn main() {
             _  => {}
         }
 
+        if shader_watcher.poll_changed() {
+            match hotreload::recompile_shaders(surface_binding.device.clone(), &vs_path, &fs_path) {
+                Ok((new_vs, new_fs)) => {
+                    vs = new_vs;
+                    fs = new_fs;
+                    // reuse the same pipeline/command-buffer rebuild the resize branch does
+                    window_resized = true;
+                },
+                Err(e) => eprintln!("shader hot-reload failed, keeping last-good pipeline: {}", e),
+            }
+        }
+
         if window_resized {
             window_resized = false;
 
-            if let Some(values) = 
-                renderer::recreate_swapchain(state.surface.clone(), state.swapchain.clone(), render_pass.clone()) 
-            {
-                (state.swapchain, state.swapchain_images, framebuffers) = values;
-            };
+            swapchain_binding.recreate(&surface_binding);
+            images_in_flight = vec![None; swapchain_binding.swapchain_images.len()];
 
-            viewport.dimensions = state.surface.window().inner_size().into();
-            let new_pipeline = renderer::get_pipeline::<Vertex>(
-                state.device.clone(),
+            viewport.dimensions = surface_binding.surface.window().inner_size().into();
+            frame_graph = build_frame_graph(
+                &surface_binding,
+                &swapchain_binding,
                 vs.clone(),
                 fs.clone(),
-                render_pass.clone(),
-                viewport.clone()
-            );
-            command_buffers = renderer::get_command_buffers(
-                state.device.clone(),
-                state.queue.clone(),
-                new_pipeline,
-                &framebuffers,
-                vertex_buffer.clone()
+                viewport.clone(),
+                vertex_buffer.clone(),
+                texture_view.clone(),
+                sampler.clone(),
+                config.clear_color,
             );
+            command_buffers = frame_graph.record_command_buffers(surface_binding.device.clone(), surface_binding.queue.clone());
         };
 
 
         //update the game here
         let (image_i, suboptimal, acquire_future) =
-            match swapchain::acquire_next_image(state.swapchain.clone(), None) {
+            match swapchain::acquire_next_image(swapchain_binding.swapchain.clone(), None) {
                 Ok(r) => r,
                 Err(AcquireError::OutOfDate) => {
                     window_resized = true;
@@ -159,18 +255,33 @@ fn main() {
             window_resized = true;
         }
 
-        if let Some(image_fence) = &fences[image_i] {
-            image_fence.wait(None).unwrap()
+        let frame = current_frame % MAX_FRAMES_IN_FLIGHT;
+
+        // Wait for the frame slot we're about to reuse, then for whatever
+        // frame (if any) is still presenting the image we just acquired --
+        // the two can be different frames when frames-in-flight and
+        // swapchain-image counts don't match.
+        if let Some(frame_fence) = &frame_fences[frame] {
+            frame_fence.wait(None).unwrap()
         }
 
-        let previous_future = match fences[previous_fence_i].clone() {
+        if let Some(image_frame) = images_in_flight[image_i] {
+            if image_frame != frame {
+                if let Some(image_fence) = &frame_fences[image_frame] {
+                    image_fence.wait(None).unwrap()
+                }
+            }
+        }
+        images_in_flight[image_i] = Some(frame);
+
+        let previous_future = match frame_fences[frame].clone() {
             None => {
-                let mut now = sync::now(state.device.clone());
+                let mut now = sync::now(surface_binding.device.clone());
                 now.cleanup_finished();
 
                 now.boxed()
             }
-            
+
             Some(fence) => fence.boxed()
         };
 
@@ -178,14 +289,14 @@ fn main() {
         //if we fail to execute a command buffer, gracefully set this fence to none
         let result = previous_future
             .join(acquire_future)
-            .then_execute(state.queue.clone(), command_buffers[image_i].clone());
-        
+            .then_execute(surface_binding.queue.clone(), command_buffers[image_i].clone());
+
         if let Ok(cb_future) = result {
             let future = cb_future
-                .then_swapchain_present(state.queue.clone(), state.swapchain.clone(), image_i)
+                .then_swapchain_present(surface_binding.queue.clone(), swapchain_binding.swapchain.clone(), image_i)
                 .then_signal_fence_and_flush();
 
-            fences[image_i] = match future {
+            frame_fences[frame] = match future {
                 Ok(value) => Some(Arc::new(value)),
                 Err(FlushError::OutOfDate) => {
                     window_resized = true;
@@ -201,6 +312,6 @@ fn main() {
             return
         }
 
-        previous_fence_i = image_i;
+        current_frame += 1;
     });
 }
@@ -0,0 +1,338 @@
+// A small render graph: nodes declare the resources they read and write, the
+// graph topologically sorts them by those dependencies, allocates the
+// intermediate attachment images, and emits one command buffer per swapchain
+// image with each node's render pass run in dependency order.
+//
+// First version: graphics-only, single queue, one subpass per node. This is
+// enough to add passes like a shadow map or a post-process blit ahead of the
+// triangle's own pass without hardwiring a second render pass into `main`.
+// It does not yet do cross-node resource synchronization beyond subpass
+// ordering, and every node owns exactly one render pass/framebuffer set.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, SubpassContents},
+    device::{Device, Queue},
+    format::{ClearValue, Format},
+    image::{view::ImageView, AttachmentImage, ImageAccess, SwapchainImage},
+    pipeline::GraphicsPipeline,
+    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass},
+};
+
+use winit::window::Window;
+
+/// A resource produced by one node and consumed by others. Declared up front
+/// via `RenderGraphBuilder::swapchain_resource`/`attachment_resource`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResourceId(usize);
+
+/// A node registered with a `RenderGraphBuilder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+enum ResourceKind {
+    Swapchain,
+    Attachment { format: Format },
+}
+
+/// Records a node's draw calls for swapchain image `image_i` into an
+/// already-`begin_render_pass`'d command buffer, with the compiled pipeline
+/// for building matching descriptor sets. The graph handles
+/// `bind_pipeline_graphics`/`begin_render_pass`/`end_render_pass` itself;
+/// this closure only needs to bind vertex/descriptor data and draw.
+pub type RecordFn = Arc<dyn Fn(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, usize, &Arc<GraphicsPipeline>) + Send + Sync>;
+
+/// Builds a node's pipeline against the `RenderPass`/`Subpass` the graph
+/// derived for it and whether that render pass carries a depth/stencil
+/// attachment (mirrors the node's own `depth_format`); the graph owns
+/// render-pass construction so a node never has to hardwire its own
+/// attachment layout.
+pub type PipelineFactory = Box<dyn FnOnce(Arc<RenderPass>, bool) -> Arc<GraphicsPipeline>>;
+
+struct NodeDecl {
+    name: String,
+    inputs: Vec<ResourceId>,
+    outputs: Vec<ResourceId>,
+    // depth buffer is private to the node rather than a graph resource:
+    // nothing downstream samples it, it just needs to outlive the node's pass
+    depth_format: Option<Format>,
+    clear_color: [f32; 4],
+    pipeline_factory: PipelineFactory,
+    record: RecordFn,
+}
+
+/// Declares nodes and the resources they read/write; `compile` linearizes
+/// them into a `RenderGraph` ready to record command buffers from.
+pub struct RenderGraphBuilder {
+    device: Arc<Device>,
+    resources: Vec<ResourceKind>,
+    nodes: Vec<NodeDecl>,
+}
+
+impl RenderGraphBuilder {
+    pub fn new(device: Arc<Device>) -> Self {
+        Self { device, resources: Vec::new(), nodes: Vec::new() }
+    }
+
+    /// The swapchain image itself, as a resource a node can write to.
+    pub fn swapchain_resource(&mut self) -> ResourceId {
+        let id = ResourceId(self.resources.len());
+        self.resources.push(ResourceKind::Swapchain);
+        id
+    }
+
+    /// An intermediate image the graph allocates and owns, e.g. a shadow map
+    /// or an offscreen color target read back by a later node.
+    pub fn attachment_resource(&mut self, format: Format) -> ResourceId {
+        let id = ResourceId(self.resources.len());
+        self.resources.push(ResourceKind::Attachment { format });
+        id
+    }
+
+    /// Registers a node. `inputs`/`outputs` are only used to order nodes
+    /// relative to each other here; binding a prior node's output image as a
+    /// sampled input is still the caller's job inside `record`. `depth_format`
+    /// gives the node its own private depth buffer (not a graph resource, as
+    /// nothing downstream reads it) to enable `DepthStencilState`. `clear_color`
+    /// is used for every color output this node writes.
+    /// `pipeline_factory` is called once during `compile` with the
+    /// `RenderPass` the graph derived for this node.
+    pub fn add_node(
+        &mut self,
+        name: &str,
+        inputs: Vec<ResourceId>,
+        outputs: Vec<ResourceId>,
+        depth_format: Option<Format>,
+        clear_color: [f32; 4],
+        pipeline_factory: PipelineFactory,
+        record: RecordFn,
+    ) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(NodeDecl { name: name.to_owned(), inputs, outputs, depth_format, clear_color, pipeline_factory, record });
+        id
+    }
+
+    /// Topologically sorts the registered nodes by their resource
+    /// dependencies, builds each node's render pass/framebuffers against
+    /// `swapchain_images`, and returns the compiled graph.
+    pub fn compile(self, swapchain_images: &[Arc<SwapchainImage<Window>>]) -> RenderGraph {
+        let order = topological_order(&self.nodes);
+        let resources = self.resources;
+        let device = self.device;
+        let mut nodes: Vec<Option<NodeDecl>> = self.nodes.into_iter().map(Some).collect();
+
+        let compiled_nodes = order
+            .into_iter()
+            .map(|node_index| {
+                let node = nodes[node_index].take().expect("each node index appears once in topological order");
+                let output_format = |resource: ResourceId| match &resources[resource.0] {
+                    ResourceKind::Swapchain => swapchain_images[0].swapchain().image_format(),
+                    ResourceKind::Attachment { format } => *format,
+                };
+
+                let formats: Vec<Format> = node.outputs.iter().map(|r| output_format(*r)).collect();
+                let render_pass = build_render_pass(device.clone(), &formats, node.depth_format);
+
+                let framebuffers = swapchain_images
+                    .iter()
+                    .map(|swapchain_image| {
+                        let dimensions = swapchain_image.dimensions().width_height();
+                        let mut attachments = node
+                            .outputs
+                            .iter()
+                            .map(|resource| match &resources[resource.0] {
+                                ResourceKind::Swapchain => ImageView::new_default(swapchain_image.clone()).unwrap(),
+                                ResourceKind::Attachment { format } => {
+                                    let image = AttachmentImage::transient(device.clone(), dimensions, *format).unwrap();
+                                    ImageView::new_default(image).unwrap()
+                                }
+                            })
+                            .collect::<Vec<_>>();
+
+                        if let Some(depth_format) = node.depth_format {
+                            let depth_image = AttachmentImage::transient(device.clone(), dimensions, depth_format).unwrap();
+                            attachments.push(ImageView::new_default(depth_image).unwrap());
+                        }
+
+                        Framebuffer::new(
+                            render_pass.clone(),
+                            FramebufferCreateInfo { attachments, ..Default::default() }
+                        ).unwrap()
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut clear_values: Vec<ClearValue> = formats.iter().map(|_| ClearValue::Float(node.clear_color)).collect();
+                if node.depth_format.is_some() {
+                    clear_values.push(ClearValue::Depth(1.0));
+                }
+
+                let pipeline = (node.pipeline_factory)(render_pass.clone(), node.depth_format.is_some());
+
+                CompiledNode {
+                    name: node.name,
+                    render_pass,
+                    pipeline,
+                    framebuffers,
+                    clear_values,
+                    record: node.record,
+                }
+            })
+            .collect();
+
+        RenderGraph { nodes: compiled_nodes }
+    }
+}
+
+struct CompiledNode {
+    #[allow(dead_code)]
+    name: String,
+    #[allow(dead_code)]
+    render_pass: Arc<RenderPass>,
+    pipeline: Arc<GraphicsPipeline>,
+    framebuffers: Vec<Arc<Framebuffer>>,
+    clear_values: Vec<ClearValue>,
+    record: RecordFn,
+}
+
+/// A linearized, frame-ready set of passes. Each swapchain image gets one
+/// `PrimaryAutoCommandBuffer` that runs every node's render pass in
+/// dependency order.
+pub struct RenderGraph {
+    nodes: Vec<CompiledNode>,
+}
+
+impl RenderGraph {
+    pub fn record_command_buffers(&self, device: Arc<Device>, queue: Arc<Queue>) -> Vec<Arc<PrimaryAutoCommandBuffer>> {
+        let image_count = self.nodes.first().map(|n| n.framebuffers.len()).unwrap_or(0);
+
+        (0..image_count)
+            .map(|image_i| {
+                let mut builder = AutoCommandBufferBuilder::primary(
+                    device.clone(),
+                    queue.family(),
+                    CommandBufferUsage::MultipleSubmit
+                ).unwrap();
+
+                for node in &self.nodes {
+                    builder
+                        .begin_render_pass(
+                            node.framebuffers[image_i].clone(),
+                            SubpassContents::Inline,
+                            node.clear_values.clone()
+                        )
+                        .unwrap()
+                        .bind_pipeline_graphics(node.pipeline.clone());
+
+                    (node.record)(&mut builder, image_i, &node.pipeline);
+
+                    builder.end_render_pass().unwrap();
+                }
+
+                Arc::new(builder.build().unwrap())
+            })
+            .collect()
+    }
+}
+
+fn build_render_pass(device: Arc<Device>, formats: &[Format], depth_format: Option<Format>) -> Arc<RenderPass> {
+    // `single_pass_renderpass!` needs its attachment list at macro-expansion
+    // time, which a runtime-sized `formats` slice can't provide; build the
+    // equivalent `RenderPassCreateInfo` by hand instead.
+    use vulkano::render_pass::{AttachmentDescription, AttachmentReference, RenderPassCreateInfo, SubpassDescription};
+    use vulkano::image::{ImageLayout, SampleCount};
+
+    let mut attachments = formats
+        .iter()
+        .map(|format| AttachmentDescription {
+            format: Some(*format),
+            samples: SampleCount::Sample1,
+            load_op: vulkano::render_pass::LoadOp::Clear,
+            store_op: vulkano::render_pass::StoreOp::Store,
+            stencil_load_op: vulkano::render_pass::LoadOp::DontCare,
+            stencil_store_op: vulkano::render_pass::StoreOp::DontCare,
+            initial_layout: ImageLayout::Undefined,
+            final_layout: ImageLayout::ColorAttachmentOptimal,
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+
+    let color_attachments = (0..formats.len())
+        .map(|i| Some(AttachmentReference {
+            attachment: i as u32,
+            layout: ImageLayout::ColorAttachmentOptimal,
+            ..Default::default()
+        }))
+        .collect();
+
+    let depth_stencil_attachment = depth_format.map(|format| {
+        attachments.push(AttachmentDescription {
+            format: Some(format),
+            samples: SampleCount::Sample1,
+            load_op: vulkano::render_pass::LoadOp::Clear,
+            store_op: vulkano::render_pass::StoreOp::DontCare,
+            stencil_load_op: vulkano::render_pass::LoadOp::DontCare,
+            stencil_store_op: vulkano::render_pass::StoreOp::DontCare,
+            initial_layout: ImageLayout::Undefined,
+            final_layout: ImageLayout::DepthStencilAttachmentOptimal,
+            ..Default::default()
+        });
+
+        AttachmentReference {
+            attachment: (attachments.len() - 1) as u32,
+            layout: ImageLayout::DepthStencilAttachmentOptimal,
+            ..Default::default()
+        }
+    });
+
+    RenderPass::new(device, RenderPassCreateInfo {
+        attachments,
+        subpasses: vec![SubpassDescription {
+            color_attachments,
+            depth_stencil_attachment,
+            ..Default::default()
+        }],
+        ..Default::default()
+    }).unwrap()
+}
+
+// Kahn's algorithm over each node's resource reads/writes: an edge runs from
+// the node that writes a resource to every node that later reads it.
+fn topological_order(nodes: &[NodeDecl]) -> Vec<usize> {
+    let mut producer_of: HashMap<usize, usize> = HashMap::new();
+    for (i, node) in nodes.iter().enumerate() {
+        for output in &node.outputs {
+            producer_of.insert(output.0, i);
+        }
+    }
+
+    let mut dependencies: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    let mut in_degree = vec![0usize; nodes.len()];
+    for (i, node) in nodes.iter().enumerate() {
+        for input in &node.inputs {
+            if let Some(&producer) = producer_of.get(&input.0) {
+                if producer != i {
+                    dependencies[producer].push(i);
+                    in_degree[i] += 1;
+                }
+            }
+        }
+    }
+
+    let mut ready: Vec<usize> = (0..nodes.len()).filter(|&i| in_degree[i] == 0).collect();
+    let mut order = Vec::with_capacity(nodes.len());
+
+    while let Some(node_index) = ready.pop() {
+        order.push(node_index);
+        for &dependent in &dependencies[node_index] {
+            in_degree[dependent] -= 1;
+            if in_degree[dependent] == 0 {
+                ready.push(dependent);
+            }
+        }
+    }
+
+    assert_eq!(order.len(), nodes.len(), "render graph has a resource dependency cycle");
+    order
+}
@@ -1,7 +1,10 @@
 use vulkano::{
-    instance::{Instance, InstanceCreateInfo},
+    instance::{
+        Instance, InstanceCreateInfo, InstanceExtensions,
+        debug::{DebugCallback, Message, MessageSeverity, MessageType},
+    },
     device::{
-        physical::{QueueFamily, PhysicalDevice, PhysicalDeviceType},
+        physical::{QueueFamily, PhysicalDevice},
         Device,
         DeviceCreateInfo,
         QueueCreateInfo,
@@ -9,67 +12,129 @@ use vulkano::{
         Queue,
     },
     format::Format,
-    render_pass::{RenderPass, Subpass, Framebuffer, FramebufferCreateInfo},
-    image::{
-        ImageUsage,
-        SwapchainImage,
-        view::ImageView
-    }, 
+    render_pass::{RenderPass, Subpass},
+    image::{ImageUsage, SwapchainImage},
     swapchain::{self, Surface, Swapchain, SwapchainCreateInfo, AcquireError, SwapchainCreationError},
-    buffer::{BufferUsage, CpuAccessibleBuffer, BufferAccess},
     pipeline::{
         GraphicsPipeline,
         graphics::{
-           input_assembly::InputAssemblyState,  
-           vertex_input::{BuffersDefinition, VertexBuffersCollection},
+           depth_stencil::DepthStencilState,
+           input_assembly::InputAssemblyState,
+           vertex_input::BuffersDefinition,
            viewport::{Viewport, ViewportState},
         }
     },
     shader::ShaderModule,
-    command_buffer::{PrimaryAutoCommandBuffer, AutoCommandBufferBuilder, CommandBufferUsage, SubpassContents},
     sync::{self, GpuFuture, FlushError, FenceSignalFuture},
 };
 
 use vulkano_win::VkSurfaceBuild;
 
+use crate::config::RendererConfig;
 
 use winit::{
     event_loop::EventLoop,
     window::{WindowBuilder, Window}
 };
 
+use std::env;
 use std::sync::Arc;
 
-pub struct VulkanState<'a> {
+// Set UNIVERSES_VALIDATION=1 to enable the Khronos validation layer and a
+// debug messenger that logs driver-side misuse (bad image layouts, missing
+// barriers, etc.) that would otherwise fail silently or crash opaquely.
+const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+// depth precision handed to `render_graph` nodes that enable a depth buffer
+pub(crate) const DEPTH_FORMAT: Format = Format::D16_UNORM;
+
+fn validation_enabled() -> bool {
+    env::var("UNIVERSES_VALIDATION").map(|v| v == "1").unwrap_or(false)
+}
+
+// Instance/device/queue: stable for the program's life, unaffected by resize.
+pub struct SurfaceBinding<'a> {
     pub instance: Arc<Instance>,
+    // kept alive for the lifetime of the instance; dropping it unregisters the callback
+    _debug_callback: Option<DebugCallback>,
     pub event_loop: EventLoop<()>,
     pub surface: Arc<Surface<Window>>,
     pub physical_device: Option<PhysicalDevice<'a>>,
     pub device: Arc<Device>,
     pub queue: Arc<Queue>,
+}
+
+// Swapchain/images: rebuilt every time the window is resized. Framebuffers
+// are no longer tracked here now that `render_graph` derives its own
+// render pass per node and can need more than one framebuffer set; callers
+// recompile their `RenderGraph` against the new images after `recreate`.
+pub struct SwapchainBinding {
     pub swapchain: Arc<Swapchain<Window>>,
     pub swapchain_images: Vec<Arc<SwapchainImage<Window>>>,
-    pub image_format: Format,
 }
 
-pub fn init_vulkan<'a>() -> VulkanState<'a> {
-    let extensions = vulkano_win::required_extensions();
+impl SwapchainBinding {
+    // Rebuilds the swapchain and its images in place for `surface_binding`'s
+    // current window size. No-op if the new extent isn't supported yet
+    // (happens transiently while a resize is in progress); the caller will
+    // get another resize event once it settles.
+    pub fn recreate(&mut self, surface_binding: &SurfaceBinding) {
+        let new_dimensions = surface_binding.surface.window().inner_size();
+
+        let (new_swapchain, new_images) = match self.swapchain.recreate(SwapchainCreateInfo {
+            image_extent: new_dimensions.into(),
+            ..self.swapchain.create_info()
+        }) {
+            Ok(r) => r,
+            Err(SwapchainCreationError::ImageExtentNotSupported { .. }) => return,
+            Err(e) => panic!("Failed to recreate swapchain: {:?}", e),
+        };
+
+        self.swapchain = new_swapchain;
+        self.swapchain_images = new_images;
+    }
+}
+
+pub fn init_vulkan<'a>(config: &RendererConfig) -> (SurfaceBinding<'a>, SwapchainBinding) {
+    let validation = validation_enabled();
+
+    let mut extensions = vulkano_win::required_extensions();
+    if validation {
+        extensions = InstanceExtensions {
+            ext_debug_utils: true,
+            ..extensions
+        };
+    }
+
     let device_extensions = DeviceExtensions {
         khr_swapchain: true,
         ..DeviceExtensions::none()
     };
 
+    let enabled_layers = if validation {
+        vec![VALIDATION_LAYER.to_owned()]
+    } else {
+        Vec::new()
+    };
+
     let instance = Instance::new(InstanceCreateInfo {
         enabled_extensions: extensions,
+        enabled_layers,
         ..Default::default()
     }).unwrap();
 
+    let debug_callback = if validation {
+        setup_debug_callback(&instance)
+    } else {
+        None
+    };
+
     let event_loop = EventLoop::new();
     let surface = WindowBuilder::new()
         .build_vk_surface(&event_loop, instance.clone())
         .unwrap();
 
-    let (physical_device, queue_family) = select_physical_device(&instance, surface.clone(), &device_extensions);
+    let (physical_device, queue_family) = select_physical_device(&instance, surface.clone(), &device_extensions, config);
 
     let (device, mut queues) = Device::new(
         physical_device,
@@ -100,33 +165,84 @@ pub fn init_vulkan<'a>() -> VulkanState<'a> {
         device.clone(),
         surface.clone(),
         SwapchainCreateInfo {
-            min_image_count: caps.min_image_count + 1,
+            min_image_count: config.min_image_count.unwrap_or(caps.min_image_count + 1),
             image_format,
             image_extent: dimensions.into(),
             image_usage: ImageUsage::color_attachment(),
             composite_alpha,
+            present_mode: config.present_mode.to_vulkan(),
             ..Default::default()
         }
     ).unwrap();
     
-    VulkanState {
+    let surface_binding = SurfaceBinding {
         instance,
+        _debug_callback: debug_callback,
         event_loop,
         surface,
         physical_device: None,
         device,
         queue,
+    };
+
+    let swapchain_binding = SwapchainBinding {
         swapchain,
         swapchain_images: images,
-        image_format: image_format.unwrap(),
-    }
+    };
+
+    (surface_binding, swapchain_binding)
+}
+
+fn setup_debug_callback(instance: &Arc<Instance>) -> Option<DebugCallback> {
+    let severity = MessageSeverity {
+        error: true,
+        warning: true,
+        information: true,
+        verbose: true,
+    };
+    let ty = MessageType::all();
 
+    DebugCallback::new(instance, severity, ty, message_callback).ok()
+}
+
+fn message_callback(msg: &Message) {
+    let severity = if msg.severity.error {
+        "error"
+    } else if msg.severity.warning {
+        "warning"
+    } else if msg.severity.information {
+        "information"
+    } else if msg.severity.verbose {
+        "verbose"
+    } else {
+        "unknown"
+    };
+
+    let ty = if msg.ty.general {
+        "general"
+    } else if msg.ty.validation {
+        "validation"
+    } else if msg.ty.performance {
+        "performance"
+    } else {
+        "unknown"
+    };
+
+    if msg.severity.error {
+        eprintln!("[vulkan:{}:{}] {}: {}", severity, ty, msg.layer_prefix.unwrap_or("unknown"), msg.description);
+    } else {
+        log::log!(
+            if msg.severity.warning { log::Level::Warn } else { log::Level::Info },
+            "[vulkan:{}:{}] {}: {}", severity, ty, msg.layer_prefix.unwrap_or("unknown"), msg.description
+        );
+    }
 }
 
 fn select_physical_device<'b>(
     instance: &'b Arc<Instance>,
     _surface: Arc<Surface<Window>>,
     device_extensions: &DeviceExtensions,
+    config: &RendererConfig,
 ) -> (PhysicalDevice<'b>, QueueFamily<'b>) {
     let (physical_device, queue_family) = PhysicalDevice::enumerate(&instance)
         .filter(|&p| p.supported_extensions().is_superset_of(&device_extensions))
@@ -135,127 +251,40 @@ fn select_physical_device<'b>(
                 .find(|&q| q.supports_graphics()) //removed a surface.is_supported because this no longer exists
                 .map(|q| (p, q))
         })
-        .min_by_key(|(p, _)| match p.properties().device_type {
-            PhysicalDeviceType::DiscreteGpu => 0,
-            PhysicalDeviceType::IntegratedGpu => 1,
-            PhysicalDeviceType::VirtualGpu => 2,
-            PhysicalDeviceType::Cpu => 3,
-            PhysicalDeviceType::Other => 4,
-        })
+        .min_by_key(|(p, _)| config.device_rank(p.properties().device_type))
         .expect("no device available");
 
     (physical_device, queue_family)
 }
 
-pub fn get_render_pass(device: Arc<Device>, image_format: &Format) -> Arc<RenderPass> {
-    vulkano::single_pass_renderpass!(device.clone(),
-        attachments: {
-            color: {
-                load: Clear,
-                store: Store,
-                format: *image_format,
-                samples: 1,
-            }
-        },
-        pass: {
-            color: [color],
-            depth_stencil: {}
-        }
-    ).unwrap()
-}
-
-pub fn get_frame_buffers(images: &[Arc<SwapchainImage<Window>>], render_pass: Arc<RenderPass>) -> Vec<Arc<Framebuffer>> {
-    images
-        .iter()
-        .map(|image| {
-            let view = ImageView::new_default(image.clone()).unwrap();
-            Framebuffer::new(
-                render_pass.clone(), 
-                FramebufferCreateInfo {
-                    attachments: vec![view],
-                    ..Default::default()
-                }
-            ).unwrap()
-        })
-        .collect::<Vec<_>>()
-}
-
+// `depth_enabled` must agree with whether `render_pass` actually carries a
+// depth/stencil attachment (the node's `depth_format` in `render_graph`) --
+// enabling `DepthStencilState` against a render pass with no depth
+// attachment is a pipeline/render-pass mismatch.
 pub fn get_pipeline<T>(
     device: Arc<Device>,
     vs: Arc<ShaderModule>,
     fs: Arc<ShaderModule>,
     render_pass: Arc<RenderPass>,
-    viewport: Viewport
-) -> Arc<GraphicsPipeline> 
+    viewport: Viewport,
+    depth_enabled: bool,
+) -> Arc<GraphicsPipeline>
 where
     T: vulkano::pipeline::graphics::vertex_input::Vertex
 {
-    GraphicsPipeline::start()
+    let mut builder = GraphicsPipeline::start()
         .vertex_input_state(BuffersDefinition::new().vertex::<T>())
         .vertex_shader(vs.entry_point("main").unwrap(), ())
         .input_assembly_state(InputAssemblyState::new())
         .viewport_state(ViewportState::viewport_fixed_scissor_irrelevant([viewport]))
-        .fragment_shader(fs.entry_point("main").unwrap(), ())
+        .fragment_shader(fs.entry_point("main").unwrap(), ());
+
+    if depth_enabled {
+        builder = builder.depth_stencil_state(DepthStencilState::simple_depth_test());
+    }
+
+    builder
         .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
         .build(device.clone())
         .unwrap()
 }
-
-pub fn get_command_buffers<T>(
-    device: Arc<Device>,
-    queue: Arc<Queue>,
-    pipeline: Arc<GraphicsPipeline>,
-    framebuffers: &Vec<Arc<Framebuffer>>,
-    vertex_buffer: Arc<CpuAccessibleBuffer<[T]>>
-) -> Vec<Arc<PrimaryAutoCommandBuffer>> 
-where
-    T: std::marker::Sync + std::marker::Send + bytemuck::Pod
-{
-    framebuffers
-        .iter()
-        .map(|framebuffer| {
-            let mut builder = AutoCommandBufferBuilder::primary(
-                device.clone(),
-                queue.family(),
-                CommandBufferUsage::MultipleSubmit
-            ).unwrap();
-
-            builder
-                .begin_render_pass(
-                    framebuffer.clone(),
-                    SubpassContents::Inline,
-                    vec![[0.0, 0.0, 0.0, 1.0].into()]
-                )
-                .unwrap()
-                .bind_pipeline_graphics(pipeline.clone())
-                .bind_vertex_buffers(0, vertex_buffer.clone())
-                .draw(vertex_buffer.read().unwrap().len() as u32, 1, 0, 0)
-                .unwrap()
-                .end_render_pass()
-                .unwrap();
-
-            Arc::new(builder.build().unwrap())
-        })
-        .collect()
-}
-
-pub fn recreate_swapchain(
-    surface: Arc<Surface<Window>>,
-    swapchain: Arc<Swapchain<Window>>,    
-    render_pass: Arc<RenderPass>
-) -> Option<(Arc<Swapchain<Window>>, Vec<Arc<SwapchainImage<Window>>>, Vec<Arc<Framebuffer>>)> {
-
-    let new_dimensions = surface.window().inner_size();
-
-    let (new_swapchain, new_images) = match swapchain.recreate(SwapchainCreateInfo {
-        image_extent: new_dimensions.into(),
-        ..swapchain.create_info()
-    }) {
-        Ok(r) => r,
-        Err(SwapchainCreationError::ImageExtentNotSupported { .. }) => return None,
-        Err(e) => panic!("Failed to recreate swapchain: {:?}", e),
-    }; 
-    let new_framebuffers = get_frame_buffers(&new_images, render_pass.clone());
-
-    Some((new_swapchain, new_images, new_framebuffers))
-}
@@ -0,0 +1,108 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use vulkano::{
+    descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet},
+    device::{Device, Queue},
+    format::Format,
+    image::{view::ImageView, ImageDimensions, ImmutableImage, MipmapsCount},
+    pipeline::{GraphicsPipeline, Pipeline},
+    sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode},
+    sync::GpuFuture,
+};
+
+// Loads a PNG from disk into a device-local `ImmutableImage` and waits for the
+// upload to finish. `path` is read straight from the source tree (typically
+// under `RendererConfig::asset_root`), the same way `hotreload` reads shader
+// sources -- the `assets` copy `build.rs` puts in `OUT_DIR` is for bundling a
+// distributable build, not something any runtime loader reads from. The
+// upload goes through `queue` alone -- no separate `Device` handle is needed
+// here.
+//
+// `ImmutableImage::from_iter` with `Format::R8G8B8A8_SRGB` requires exactly
+// 4 bytes per texel, but a PNG's native layout depends on its color type and
+// bit depth (3 B/px RGB, 1 B/px grayscale, 2/6/8 B/px at 16-bit, palette
+// indices, ...), so the decoded buffer is normalized to 8-bit RGBA below
+// rather than handed to `from_iter` as-is.
+pub fn load_texture(queue: Arc<Queue>, path: &Path) -> Arc<ImageView<ImmutableImage>> {
+    let file = File::open(path).unwrap_or_else(|e| panic!("Failed to open texture {:?}: {}", path, e));
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().expect("Failed to read PNG header");
+
+    let info = reader.info();
+    let dimensions = ImageDimensions::Dim2d {
+        width: info.width,
+        height: info.height,
+        array_layers: 1,
+    };
+
+    let mut image_data = vec![0; reader.output_buffer_size()];
+    let output_info = reader.next_frame(&mut image_data).expect("Failed to decode PNG");
+    image_data.truncate(output_info.buffer_size());
+
+    let rgba_data = to_rgba8(&image_data, output_info.color_type);
+
+    let (image, future) = ImmutableImage::from_iter(
+        rgba_data.into_iter(),
+        dimensions,
+        MipmapsCount::One,
+        Format::R8G8B8A8_SRGB,
+        queue,
+    ).expect("Failed to upload texture");
+
+    future.flush().expect("Failed to flush texture upload");
+
+    ImageView::new_default(image).unwrap()
+}
+
+// Expands an 8-bit-per-channel decode (guaranteed by
+// `Transformations::EXPAND | Transformations::STRIP_16`, which also rules
+// out `Indexed`) into tightly packed RGBA8, adding an opaque alpha channel
+// for color types that don't carry one.
+fn to_rgba8(data: &[u8], color_type: png::ColorType) -> Vec<u8> {
+    match color_type {
+        png::ColorType::Rgba => data.to_vec(),
+        png::ColorType::Rgb => data.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => data.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => data.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => unreachable!("Transformations::EXPAND expands palette images to Rgb/Rgba"),
+    }
+}
+
+pub fn build_sampler(
+    device: Arc<Device>,
+    filter: Filter,
+    mipmap_mode: SamplerMipmapMode,
+    address_mode: SamplerAddressMode,
+) -> Arc<Sampler> {
+    Sampler::new(device, SamplerCreateInfo {
+        mag_filter: filter,
+        min_filter: filter,
+        mipmap_mode,
+        address_mode: [address_mode; 3],
+        ..Default::default()
+    }).unwrap()
+}
+
+// Binds `image_view`/`sampler` into a `PersistentDescriptorSet` matching the
+// layout `pipeline` declares at `set_index` (derived from the shader's SPIR-V
+// reflection data), ready to be passed to `bind_descriptor_sets`.
+pub fn build_descriptor_set(
+    pipeline: Arc<GraphicsPipeline>,
+    set_index: usize,
+    image_view: Arc<ImageView<ImmutableImage>>,
+    sampler: Arc<Sampler>,
+) -> Arc<PersistentDescriptorSet> {
+    let layout = pipeline
+        .layout()
+        .set_layouts()
+        .get(set_index)
+        .expect("pipeline has no descriptor set at that index");
+
+    PersistentDescriptorSet::new(
+        layout.clone(),
+        [WriteDescriptorSet::image_view_sampler(0, image_view, sampler)],
+    ).unwrap()
+}